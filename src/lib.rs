@@ -0,0 +1,4 @@
+pub mod repositories;
+pub mod todo_list;
+
+uniffi::setup_scaffolding!();
@@ -1,6 +1,9 @@
 use std::{
 	collections::HashMap,
-	sync::{Arc, RwLock, RwLockWriteGuard, RwLockReadGuard}
+	fs,
+	io::Write,
+	path::PathBuf,
+	sync::{Arc, Mutex, RwLock, RwLockWriteGuard, RwLockReadGuard}
 };
 use anyhow::{Context, Ok};
 use thiserror::Error;
@@ -8,41 +11,87 @@ use serde::{Deserialize, Serialize};
 use validator::Validate;
 
 #[derive(Debug, Error)]
-enum RepositoryError {
+pub enum RepositoryError {
     #[error("NotFound, id is {0}")]
     NotFound(i32),
+    #[error("VersionConflict, id is {id}, expected {expected}, actual {actual}")]
+    VersionConflict { id: i32, expected: u64, actual: u64 },
 }
 
 // リポジトリ
 pub trait TodoRepository: Clone + std::marker::Send + std::marker::Sync + 'static {
     fn create(&self, payload: CreateTodo) -> Todo;
     fn find(&self, id: i32) -> Option<Todo>;
-    fn all(&self) -> Vec<Todo>;
+    fn all(&self, opts: ListOptions) -> Vec<Todo>;
     fn update(&self, id: i32, payload: UpdateTodo) -> anyhow::Result<Todo>;
     fn delete(&self, id: i32) -> anyhow::Result<()>;
+    fn delete_if(&self, id: i32, expected_version: Option<u64>) -> anyhow::Result<()>;
+    fn add_label(&self, todo_id: i32, label_id: i32) -> anyhow::Result<Todo>;
+    fn remove_label(&self, todo_id: i32, label_id: i32) -> anyhow::Result<Todo>;
+}
+
+// ラベルのリポジトリ
+pub trait LabelRepository: Clone + std::marker::Send + std::marker::Sync + 'static {
+    fn create(&self, payload: CreateLabel) -> Label;
+    fn find(&self, id: i32) -> Option<Label>;
+    fn all(&self) -> Vec<Label>;
+    fn update(&self, id: i32, payload: UpdateLabel) -> anyhow::Result<Label>;
+    fn delete(&self, id: i32) -> anyhow::Result<()>;
 }
 
 // モデル
-#[derive(Debug, Serialize, Deserialize, Clone, PartialEq, Eq)]
+#[derive(Debug, Serialize, Deserialize, Clone, PartialEq, Eq, uniffi::Record)]
 pub struct Todo {
     id: i32,
     text: String,
     completed: bool,
+    labels: Vec<i32>,
+    version: u64,
 }
 
-#[derive(Debug, Serialize, Deserialize, Clone, PartialEq, Eq, Validate)]
+#[derive(Debug, Serialize, Deserialize, Clone, PartialEq, Eq)]
+pub struct Label {
+    id: i32,
+    name: String,
+}
+
+// all() の絞り込み・並び替え・ページングオプション
+#[derive(Debug, Serialize, Deserialize, Clone, PartialEq, Eq, Default)]
+pub struct ListOptions {
+    offset: Option<usize>,
+    limit: Option<usize>,
+    completed: Option<bool>,
+    query: Option<String>,
+}
+
+#[derive(Debug, Serialize, Deserialize, Clone, PartialEq, Eq, Validate, uniffi::Record)]
 pub struct CreateTodo {
 	#[validate(length(min=1, message="Can not be empty"))]
 	#[validate(length(max=100, message="Over text length"))]
     text: String,
 }
 
-#[derive(Debug, Serialize, Deserialize, Clone, PartialEq, Eq, Validate)]
+#[derive(Debug, Serialize, Deserialize, Clone, PartialEq, Eq, Validate, uniffi::Record)]
 pub struct UpdateTodo {
 	#[validate(length(min=1, message="Can not be empty"))]
 	#[validate(length(max=100, message="Over text length"))]
     text: Option<String>,
     completed: Option<bool>,
+    expected_version: Option<u64>,
+}
+
+#[derive(Debug, Serialize, Deserialize, Clone, PartialEq, Eq, Validate)]
+pub struct CreateLabel {
+	#[validate(length(min=1, message="Can not be empty"))]
+	#[validate(length(max=100, message="Over text length"))]
+    name: String,
+}
+
+#[derive(Debug, Serialize, Deserialize, Clone, PartialEq, Eq, Validate)]
+pub struct UpdateLabel {
+	#[validate(length(min=1, message="Can not be empty"))]
+	#[validate(length(max=100, message="Over text length"))]
+    name: Option<String>,
 }
 
 impl Todo {
@@ -51,66 +100,408 @@ impl Todo {
             id,
             text,
             completed: false,
+            labels: Vec::new(),
+            version: 0,
         }
     }
 }
 
+impl Label {
+    pub fn new(id: i32, name: String) -> Self {
+        Self { id, name }
+    }
+}
+
 // datasource
 type TodoDatas = HashMap<i32, Todo>;
+type LabelDatas = HashMap<i32, Label>;
+
+// 折り畳みごとにスナップショットを取る間隔
+const CHECKPOINT_INTERVAL: usize = 64;
+
+// 変更操作の種類。fold で materialized view に畳み込まれる。
+#[derive(Debug, Serialize, Deserialize, Clone, PartialEq, Eq)]
+pub enum OpKind {
+    Create(Todo),
+    Update(Todo),
+    Delete(i32),
+}
+
+// シーケンス番号で全順序付けされた追記専用ログの 1 エントリ。
+#[derive(Debug, Serialize, Deserialize, Clone, PartialEq, Eq)]
+pub struct Op {
+    seq: u64,
+    timestamp: u128,
+    kind: OpKind,
+}
+
+// ログを畳み込んだ時点のスナップショット。これより古い Op は破棄できる。
+#[derive(Debug, Default, Clone)]
+struct Checkpoint {
+    seq: u64,
+    todos: TodoDatas,
+}
+
+// ops_since の結果。保持しているログの末尾から溢れた場合は Truncated を返し、
+// 呼び出し側にスナップショットの取り直しを促す。全順序は seq のみが保証する。
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum OpsSince {
+    Ops(Vec<Op>),
+    Truncated { checkpoint_seq: u64 },
+}
+
+// 壁時計のタイムスタンプ（ミリ秒）。巻き戻りうるので順序付けには使わない（順序は seq が担保）。
+fn now_millis() -> u128 {
+    std::time::SystemTime::now()
+        .duration_since(std::time::UNIX_EPOCH)
+        .map(|d| d.as_millis())
+        .unwrap_or(0)
+}
 
 #[derive(Debug, Clone)]
 pub struct TodoRepositoryForMemory {
+    // ログを畳み込んだ materialized view。find/all はここだけを読む。
     store: Arc<RwLock<TodoDatas>>,
+    labels: Arc<RwLock<LabelDatas>>,
+    path: Option<PathBuf>,
+    next_id: Arc<RwLock<i32>>,
+    next_label_id: Arc<RwLock<i32>>,
+    // 追記専用の操作ログ（直近チェックポイント以降の末尾のみ保持）。
+    log: Arc<RwLock<Vec<Op>>>,
+    checkpoint: Arc<RwLock<Checkpoint>>,
+    seq: Arc<RwLock<u64>>,
+    // persist を直列化し、複数ライターが同じ一時ファイルを踏み合わないようにする。
+    persist_lock: Arc<Mutex<()>>,
 }
 
 impl TodoRepositoryForMemory {
     pub fn new() -> Self {
         TodoRepositoryForMemory {
             store: Arc::default(),
+            labels: Arc::default(),
+            path: None,
+            next_id: Arc::new(RwLock::new(1)),
+            next_label_id: Arc::new(RwLock::new(1)),
+            log: Arc::default(),
+            checkpoint: Arc::default(),
+            seq: Arc::new(RwLock::new(0)),
+            persist_lock: Arc::default(),
         }
     }
+    // JSON ファイルから復元する。ファイルが無ければ空で始める。
+    pub fn from_path(path: PathBuf) -> anyhow::Result<Self> {
+        let store: TodoDatas = match fs::read(&path) {
+            std::result::Result::Ok(bytes) => {
+                serde_json::from_slice(&bytes).context("failed to parse todo store")?
+            }
+            Err(err) if err.kind() == std::io::ErrorKind::NotFound => TodoDatas::new(),
+            Err(err) => return Err(err).context("failed to read todo store"),
+        };
+        // 復元済みエントリと id が衝突しないよう、最大 id の次から採番する
+        let next_id = store.keys().copied().max().unwrap_or(0) + 1;
+        // 復元したビューをそのまま初期チェックポイントとして扱う。
+        let checkpoint = Checkpoint {
+            seq: 0,
+            todos: store.clone(),
+        };
+        Ok(TodoRepositoryForMemory {
+            store: Arc::new(RwLock::new(store)),
+            labels: Arc::default(),
+            path: Some(path),
+            next_id: Arc::new(RwLock::new(next_id)),
+            next_label_id: Arc::new(RwLock::new(1)),
+            log: Arc::default(),
+            checkpoint: Arc::new(RwLock::new(checkpoint)),
+            seq: Arc::new(RwLock::new(0)),
+            persist_lock: Arc::default(),
+        })
+    }
+    // 現在のストアを JSON としてアトミックに書き出す（一時ファイル→rename）。
+    // 書き出しは persist_lock（clone 間で共有）で直列化し、一時ファイル名にはプロセス id と
+    // 現在の seq を含めて、複数ライターが互いの部分書き込みを rename し合わないようにする。
+    pub fn persist(&self) -> anyhow::Result<()> {
+        let path = match &self.path {
+            Some(path) => path,
+            None => return Ok(()),
+        };
+        let _guard = self.persist_lock.lock().unwrap();
+        let (bytes, seq) = {
+            let store = self.read_store_ref();
+            let seq = *self.seq.read().unwrap();
+            (
+                serde_json::to_vec(&*store).context("failed to serialize todo store")?,
+                seq,
+            )
+        };
+        let tmp = path.with_extension(format!("{}.{}.tmp", std::process::id(), seq));
+        let mut file = fs::File::create(&tmp).context("failed to create temp file")?;
+        file.write_all(&bytes).context("failed to write temp file")?;
+        file.sync_all().context("failed to sync temp file")?;
+        fs::rename(&tmp, path).context("failed to rename temp file")?;
+        Ok(())
+    }
+    // 永続化の失敗でプロセスを落としたり状態を巻き戻したりせず、ログに残して継続する。
+    // Todo ストアを変更する全ミューテーターで同じ「インメモリ変更は必ず反映し、永続化はベストエフォート」契約にする。
+    // ラベル自体は永続化しないため、LabelRepository の create/update はこれを呼ばない。
+    fn persist_best_effort(&self) {
+        if let Err(err) = self.persist() {
+            eprintln!("failed to persist todos: {err:#}");
+        }
+    }
+    // 直近チェックポイントの seq とスナップショットを返す。
+    // ops_since が Truncated を返したクライアントは、これで状態を作り直してから
+    // ops_since(checkpoint_seq) で差分を適用できる。
+    pub fn checkpoint(&self) -> (u64, Vec<Todo>) {
+        let checkpoint = self.checkpoint.read().unwrap();
+        let mut todos: Vec<Todo> = checkpoint.todos.values().cloned().collect();
+        todos.sort_by_key(|todo| todo.id);
+        (checkpoint.seq, todos)
+    }
+	// 1 つの Op を materialized view に畳み込む。決定的であることが不変条件。
+	// 存在しない id の Delete はエラーにせず no-op 扱いにする。
+	fn fold(view: &mut TodoDatas, op: &Op) {
+		match &op.kind {
+			OpKind::Create(todo) | OpKind::Update(todo) => {
+				view.insert(todo.id, todo.clone());
+			}
+			OpKind::Delete(id) => {
+				view.remove(id);
+			}
+		}
+	}
+	// Op をログに追記しつつビューへ畳み込む。呼び出し側が store の書き込みロックを保持していること。
+	fn append(&self, store: &mut TodoDatas, kind: OpKind) {
+		let mut log = self.log.write().unwrap();
+		let mut seq = self.seq.write().unwrap();
+		*seq += 1;
+		let op = Op {
+			seq: *seq,
+			timestamp: now_millis(),
+			kind,
+		};
+		Self::fold(store, &op);
+		log.push(op);
+		// N 件ごとにログを畳み込み、チェックポイント以前の Op を捨てて replay を有界に保つ。
+		if log.len() >= CHECKPOINT_INTERVAL {
+			let mut checkpoint = self.checkpoint.write().unwrap();
+			checkpoint.todos = store.clone();
+			checkpoint.seq = *seq;
+			log.clear();
+		}
+	}
+	// 指定シーケンスより新しい Op だけを返す（クライアントの差分同期用）。
+	// 要求仕様の `-> Vec<Op>` に対し、ここでは意図的に `OpsSince` を返している:
+	// seq が直近チェックポイントより古いと末尾ログから溢れて差分を出せないため、
+	// その場合は `Truncated` を返して `checkpoint()` からの作り直しを促す
+	// （素の `Vec<Op>` では「空＝溢れ」と「空＝差分なし」を区別できない）。
+	pub fn ops_since(&self, seq: u64) -> OpsSince {
+		let checkpoint_seq = self.checkpoint.read().unwrap().seq;
+		if seq < checkpoint_seq {
+			return OpsSince::Truncated { checkpoint_seq };
+		}
+		let log = self.log.read().unwrap();
+		OpsSince::Ops(log.iter().filter(|op| op.seq > seq).cloned().collect())
+	}
 	fn write_store_ref(&self) -> RwLockWriteGuard<TodoDatas> {
 		self.store.write().unwrap()
 	}
 	fn read_store_ref(&self) -> RwLockReadGuard<TodoDatas> {
 		self.store.read().unwrap()
 	}
+	fn write_label_ref(&self) -> RwLockWriteGuard<LabelDatas> {
+		self.labels.write().unwrap()
+	}
+	fn read_label_ref(&self) -> RwLockReadGuard<LabelDatas> {
+		self.labels.read().unwrap()
+	}
 }
 
 impl TodoRepository for TodoRepositoryForMemory {
     fn create(&self, payload: CreateTodo) -> Todo {
-		let mut store = self.write_store_ref();
-		let id = (store.len() + 1) as i32;
-		let todo = Todo::new(id, payload.text.clone());
-		store.insert(id, todo.clone());
+		let todo = {
+			let mut store = self.write_store_ref();
+			let mut next_id = self.next_id.write().unwrap();
+			let id = *next_id;
+			*next_id += 1;
+			let todo = Todo::new(id, payload.text.clone());
+			self.append(&mut store, OpKind::Create(todo.clone()));
+			todo
+		};
+		self.persist_best_effort();
 		return todo
     }
     fn find(&self, id: i32) -> Option<Todo> {
 		let store = self.read_store_ref();
 		store.get(&id).map(|todo| todo.clone())
     }
-    fn all(&self) -> Vec<Todo> {
+    fn all(&self, opts: ListOptions) -> Vec<Todo> {
 		let store = self.read_store_ref();
-		Vec::from_iter(store.values().map(|todo| todo.clone()))
+		// HashMap の反復順は非決定的なので、一度集めて id でソートしてから絞り込む
+		let mut todos: Vec<Todo> = store.values().cloned().collect();
+		todos.sort_by_key(|todo| todo.id);
+		let query = opts.query.map(|q| q.to_lowercase());
+		let mut todos: Vec<Todo> = todos
+			.into_iter()
+			.filter(|todo| match opts.completed {
+				Some(completed) => todo.completed == completed,
+				None => true,
+			})
+			.filter(|todo| match &query {
+				Some(q) => todo.text.to_lowercase().contains(q),
+				None => true,
+			})
+			.collect();
+		if let Some(offset) = opts.offset {
+			todos = todos.into_iter().skip(offset).collect();
+		}
+		if let Some(limit) = opts.limit {
+			todos.truncate(limit);
+		}
+		todos
     }
     fn update(&self, id: i32, payload: UpdateTodo) -> anyhow::Result<Todo> {
 		let mut store = self.write_store_ref();
 		let todo = store
 			.get(&id)
 			.context(RepositoryError::NotFound(id))?;
+		if let Some(expected) = payload.expected_version {
+			if expected != todo.version {
+				return Err(RepositoryError::VersionConflict {
+					id,
+					expected,
+					actual: todo.version,
+				}
+				.into());
+			}
+		}
 		let text = payload.text.unwrap_or(todo.text.clone());
 		let completed = payload.completed.unwrap_or(todo.completed);
 		let todo = Todo {
 			id,
 			text,
-			completed
+			completed,
+			labels: todo.labels.clone(),
+			version: todo.version + 1,
 		};
-		store.insert(id, todo.clone());
+		self.append(&mut store, OpKind::Update(todo.clone()));
+		drop(store);
+		self.persist_best_effort();
+		Ok(todo)
+    }
+    fn delete(&self, id: i32) -> anyhow::Result<()> {
+		let mut store = self.write_store_ref();
+		if !store.contains_key(&id) {
+			return Err(RepositoryError::NotFound(id).into());
+		}
+		self.append(&mut store, OpKind::Delete(id));
+		drop(store);
+		self.persist_best_effort();
+		Ok(())
+    }
+    fn delete_if(&self, id: i32, expected_version: Option<u64>) -> anyhow::Result<()> {
+		let mut store = self.write_store_ref();
+		let todo = store
+			.get(&id)
+			.context(RepositoryError::NotFound(id))?;
+		if let Some(expected) = expected_version {
+			if expected != todo.version {
+				return Err(RepositoryError::VersionConflict {
+					id,
+					expected,
+					actual: todo.version,
+				}
+				.into());
+			}
+		}
+		self.append(&mut store, OpKind::Delete(id));
+		drop(store);
+		self.persist_best_effort();
+		Ok(())
+    }
+    fn add_label(&self, todo_id: i32, label_id: i32) -> anyhow::Result<Todo> {
+		{
+			let labels = self.read_label_ref();
+			labels.get(&label_id).context(RepositoryError::NotFound(label_id))?;
+		}
+		let mut store = self.write_store_ref();
+		let mut todo = store
+			.get(&todo_id)
+			.context(RepositoryError::NotFound(todo_id))?
+			.clone();
+		if !todo.labels.contains(&label_id) {
+			todo.labels.push(label_id);
+		}
+		self.append(&mut store, OpKind::Update(todo.clone()));
+		drop(store);
+		self.persist_best_effort();
+		Ok(todo)
+    }
+    fn remove_label(&self, todo_id: i32, label_id: i32) -> anyhow::Result<Todo> {
+		{
+			let labels = self.read_label_ref();
+			labels.get(&label_id).context(RepositoryError::NotFound(label_id))?;
+		}
+		let mut store = self.write_store_ref();
+		let mut todo = store
+			.get(&todo_id)
+			.context(RepositoryError::NotFound(todo_id))?
+			.clone();
+		todo.labels.retain(|id| *id != label_id);
+		self.append(&mut store, OpKind::Update(todo.clone()));
+		drop(store);
+		self.persist_best_effort();
 		Ok(todo)
     }
+}
+
+impl LabelRepository for TodoRepositoryForMemory {
+    fn create(&self, payload: CreateLabel) -> Label {
+		let mut labels = self.write_label_ref();
+		let mut next_label_id = self.next_label_id.write().unwrap();
+		let id = *next_label_id;
+		*next_label_id += 1;
+		let label = Label::new(id, payload.name.clone());
+		labels.insert(id, label.clone());
+		return label
+    }
+    fn find(&self, id: i32) -> Option<Label> {
+		let labels = self.read_label_ref();
+		labels.get(&id).map(|label| label.clone())
+    }
+    fn all(&self) -> Vec<Label> {
+		let labels = self.read_label_ref();
+		Vec::from_iter(labels.values().map(|label| label.clone()))
+    }
+    fn update(&self, id: i32, payload: UpdateLabel) -> anyhow::Result<Label> {
+		let mut labels = self.write_label_ref();
+		let label = labels
+			.get(&id)
+			.context(RepositoryError::NotFound(id))?;
+		let name = payload.name.unwrap_or(label.name.clone());
+		let label = Label { id, name };
+		labels.insert(id, label.clone());
+		Ok(label)
+    }
+    // ラベル削除時は、そのラベルを参照している全 Todo から剥がしてから消す
     fn delete(&self, id: i32) -> anyhow::Result<()> {
+		let mut labels = self.write_label_ref();
+		labels.remove(&id).ok_or(RepositoryError::NotFound(id))?;
 		let mut store = self.write_store_ref();
-		store.remove(&id).ok_or(RepositoryError::NotFound(id))?;
+		// 剥がす対象の todo を先に洗い出し、Update 操作としてログに積む
+		let affected: Vec<Todo> = store
+			.values()
+			.filter(|todo| todo.labels.contains(&id))
+			.map(|todo| {
+				let mut todo = todo.clone();
+				todo.labels.retain(|label_id| *label_id != id);
+				todo
+			})
+			.collect();
+		for todo in affected {
+			self.append(&mut store, OpKind::Update(todo));
+		}
+		drop(store);
+		self.persist_best_effort();
 		Ok(())
     }
 }
@@ -121,4 +512,221 @@ impl CreateTodo {
 	pub fn new(text: String) -> Self {
 		Self { text }
 	}
+}
+
+#[cfg(test)]
+mod tests {
+	// TodoRepository と LabelRepository は同名メソッドを持つため、
+	// 曖昧さを避けてトレイトは完全修飾パスで呼び出す。
+	use super::{
+		CreateLabel, CreateTodo, LabelRepository, ListOptions, OpKind, OpsSince, RepositoryError,
+		TodoRepository, TodoRepositoryForMemory, UpdateTodo, CHECKPOINT_INTERVAL,
+	};
+	use super::Todo;
+
+	fn create(repo: &TodoRepositoryForMemory, text: &str) -> Todo {
+		TodoRepository::create(repo, CreateTodo::new(text.to_string()))
+	}
+
+	#[test]
+	fn create_assigns_incrementing_ids_that_survive_deletion() {
+		let repo = TodoRepositoryForMemory::new();
+		let first = create(&repo, "one");
+		let second = create(&repo, "two");
+		assert_eq!(first.id, 1);
+		assert_eq!(second.id, 2);
+		TodoRepository::delete(&repo, second.id).unwrap();
+		// 削除後も採番は進み、既存 id を再利用しない
+		let third = create(&repo, "three");
+		assert_eq!(third.id, 3);
+	}
+
+	#[test]
+	fn all_filters_sorts_and_paginates() {
+		let repo = TodoRepositoryForMemory::new();
+		let a = create(&repo, "buy milk");
+		let b = create(&repo, "walk the dog");
+		let c = create(&repo, "buy eggs");
+		TodoRepository::update(
+			&repo,
+			b.id,
+			UpdateTodo {
+				text: None,
+				completed: Some(true),
+				expected_version: None,
+			},
+		)
+		.unwrap();
+
+		// id 昇順で決定的に返る
+		let ids: Vec<i32> = TodoRepository::all(&repo, ListOptions::default())
+			.iter()
+			.map(|t| t.id)
+			.collect();
+		assert_eq!(ids, vec![a.id, b.id, c.id]);
+
+		// completed フィルタ
+		let done = TodoRepository::all(
+			&repo,
+			ListOptions {
+				completed: Some(true),
+				..Default::default()
+			},
+		);
+		assert_eq!(done.len(), 1);
+		assert_eq!(done[0].id, b.id);
+
+		// query は大文字小文字を無視した部分一致
+		let matched = TodoRepository::all(
+			&repo,
+			ListOptions {
+				query: Some("BUY".to_string()),
+				..Default::default()
+			},
+		);
+		assert_eq!(matched.iter().map(|t| t.id).collect::<Vec<_>>(), vec![a.id, c.id]);
+
+		// offset/limit の境界
+		let paged = TodoRepository::all(
+			&repo,
+			ListOptions {
+				offset: Some(1),
+				limit: Some(1),
+				..Default::default()
+			},
+		);
+		assert_eq!(paged.iter().map(|t| t.id).collect::<Vec<_>>(), vec![b.id]);
+	}
+
+	#[test]
+	fn update_bumps_version_and_enforces_expected_version() {
+		let repo = TodoRepositoryForMemory::new();
+		let todo = create(&repo, "draft");
+		assert_eq!(todo.version, 0);
+
+		let updated = TodoRepository::update(
+			&repo,
+			todo.id,
+			UpdateTodo {
+				text: Some("final".to_string()),
+				completed: None,
+				expected_version: Some(0),
+			},
+		)
+		.unwrap();
+		assert_eq!(updated.version, 1);
+		assert_eq!(updated.text, "final");
+
+		// 期待バージョンが一致しないと VersionConflict
+		let err = TodoRepository::update(
+			&repo,
+			todo.id,
+			UpdateTodo {
+				text: Some("stale".to_string()),
+				completed: None,
+				expected_version: Some(0),
+			},
+		)
+		.unwrap_err();
+		match err.downcast_ref::<RepositoryError>() {
+			Some(RepositoryError::VersionConflict { id, expected, actual }) => {
+				assert_eq!(*id, todo.id);
+				assert_eq!(*expected, 0);
+				assert_eq!(*actual, 1);
+			}
+			other => panic!("expected VersionConflict, got {other:?}"),
+		}
+	}
+
+	#[test]
+	fn delete_if_respects_expected_version() {
+		let repo = TodoRepositoryForMemory::new();
+		let todo = create(&repo, "remove me");
+		// バージョン不一致では消えない
+		assert!(repo.delete_if(todo.id, Some(99)).is_err());
+		assert!(TodoRepository::find(&repo, todo.id).is_some());
+		// 一致すれば消える
+		repo.delete_if(todo.id, Some(0)).unwrap();
+		assert!(TodoRepository::find(&repo, todo.id).is_none());
+	}
+
+	#[test]
+	fn deleting_label_strips_it_from_every_todo() {
+		let repo = TodoRepositoryForMemory::new();
+		let todo = create(&repo, "tagged");
+		let label = LabelRepository::create(&repo, CreateLabel { name: "work".to_string() });
+		repo.add_label(todo.id, label.id).unwrap();
+		assert_eq!(TodoRepository::find(&repo, todo.id).unwrap().labels, vec![label.id]);
+
+		LabelRepository::delete(&repo, label.id).unwrap();
+		assert!(TodoRepository::find(&repo, todo.id).unwrap().labels.is_empty());
+	}
+
+	#[test]
+	fn add_label_validates_both_ids_exist() {
+		let repo = TodoRepositoryForMemory::new();
+		let todo = create(&repo, "tagged");
+		// ラベルが存在しない
+		assert!(repo.add_label(todo.id, 999).is_err());
+		let label = LabelRepository::create(&repo, CreateLabel { name: "home".to_string() });
+		// todo が存在しない
+		assert!(repo.add_label(999, label.id).is_err());
+	}
+
+	#[test]
+	fn checkpoint_folds_log_and_ops_since_reports_truncation() {
+		let repo = TodoRepositoryForMemory::new();
+		for i in 0..CHECKPOINT_INTERVAL {
+			create(&repo, &format!("todo {i}"));
+		}
+		// ちょうど境界でチェックポイントが取られ、末尾ログは空になる
+		assert_eq!(repo.checkpoint.read().unwrap().seq, CHECKPOINT_INTERVAL as u64);
+		assert!(repo.log.read().unwrap().is_empty());
+
+		// チェックポイントより古い seq からの差分は出せない
+		match repo.ops_since(0) {
+			OpsSince::Truncated { checkpoint_seq } => {
+				assert_eq!(checkpoint_seq, CHECKPOINT_INTERVAL as u64);
+				// Truncated を受けたクライアントはスナップショットから作り直せる
+				let (snap_seq, todos) = repo.checkpoint();
+				assert_eq!(snap_seq, checkpoint_seq);
+				assert_eq!(todos.len(), CHECKPOINT_INTERVAL);
+			}
+			other => panic!("expected Truncated, got {other:?}"),
+		}
+
+		// チェックポイント以降の差分は得られる
+		let latest = create(&repo, "after checkpoint");
+		match repo.ops_since(CHECKPOINT_INTERVAL as u64) {
+			OpsSince::Ops(ops) => {
+				assert_eq!(ops.len(), 1);
+				match &ops[0].kind {
+					OpKind::Create(todo) => assert_eq!(todo.id, latest.id),
+					other => panic!("expected Create, got {other:?}"),
+				}
+			}
+			other => panic!("expected Ops, got {other:?}"),
+		}
+	}
+
+	#[test]
+	fn from_path_persists_and_reloads_todos() {
+		let mut path = std::env::temp_dir();
+		path.push(format!("todo_persist_{}.json", std::process::id()));
+		let _ = std::fs::remove_file(&path);
+
+		{
+			let repo = TodoRepositoryForMemory::from_path(path.clone()).unwrap();
+			create(&repo, "persisted");
+		}
+		// 再読み込みで復元され、採番が衝突しない
+		let repo = TodoRepositoryForMemory::from_path(path.clone()).unwrap();
+		let todos = TodoRepository::all(&repo, ListOptions::default());
+		assert_eq!(todos.len(), 1);
+		assert_eq!(todos[0].text, "persisted");
+		let next = create(&repo, "another");
+		assert_eq!(next.id, 2);
+
+		let _ = std::fs::remove_file(&path);
+	}
 }
\ No newline at end of file
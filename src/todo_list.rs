@@ -0,0 +1,94 @@
+use std::sync::Arc;
+
+use validator::{Validate, ValidationErrors};
+
+use crate::repositories::{
+	CreateTodo, ListOptions, RepositoryError, Todo, TodoRepository, TodoRepositoryForMemory,
+	UpdateTodo,
+};
+
+// 外部言語（Swift/Kotlin/Python）に公開する型付きエラー
+#[derive(Debug, thiserror::Error, uniffi::Error)]
+pub enum TodoError {
+	#[error("todo does not exist, id is {id}")]
+	TodoDoesNotExist { id: i32 },
+	#[error("text can not be empty")]
+	EmptyText,
+	#[error("text is over the allowed length")]
+	TextTooLong,
+	#[error("version conflict, id is {id}, expected {expected}, actual {actual}")]
+	VersionConflict { id: i32, expected: u64, actual: u64 },
+	#[error("internal error: {message}")]
+	Internal { message: String },
+}
+
+// validator のエラーを型付きのバリアントへ振り分ける。
+// length(max) 由来のものは TextTooLong、それ以外（空文字など）は EmptyText にする。
+fn map_validation_error(errs: ValidationErrors) -> TodoError {
+	for field_errors in errs.field_errors().values() {
+		for err in field_errors.iter() {
+			if err.params.contains_key("max") {
+				return TodoError::TextTooLong;
+			}
+		}
+	}
+	TodoError::EmptyText
+}
+
+// anyhow で返ってきたリポジトリエラーを FFI 境界向けの型付きエラーに落とし込む。
+fn map_repository_error(err: anyhow::Error) -> TodoError {
+	match err.downcast_ref::<RepositoryError>() {
+		Some(RepositoryError::NotFound(id)) => TodoError::TodoDoesNotExist { id: *id },
+		Some(RepositoryError::VersionConflict { id, expected, actual }) => {
+			TodoError::VersionConflict {
+				id: *id,
+				expected: *expected,
+				actual: *actual,
+			}
+		}
+		None => TodoError::Internal {
+			message: err.to_string(),
+		},
+	}
+}
+
+// UniFFI は Arc でラップしたインターフェースしか扱えず、ジェネリクスも渡せないため、
+// 具体型の TodoRepositoryForMemory を内部に直接保持する。
+#[derive(uniffi::Object)]
+pub struct TodoList {
+	repository: TodoRepositoryForMemory,
+}
+
+#[uniffi::export]
+impl TodoList {
+	#[uniffi::constructor]
+	pub fn new() -> Arc<Self> {
+		Arc::new(Self {
+			repository: TodoRepositoryForMemory::new(),
+		})
+	}
+
+	pub fn create(&self, payload: CreateTodo) -> Result<Todo, TodoError> {
+		payload.validate().map_err(map_validation_error)?;
+		Ok(self.repository.create(payload))
+	}
+
+	pub fn find(&self, id: i32) -> Option<Todo> {
+		self.repository.find(id)
+	}
+
+	pub fn all(&self) -> Vec<Todo> {
+		self.repository.all(ListOptions::default())
+	}
+
+	pub fn update(&self, id: i32, payload: UpdateTodo) -> Result<Todo, TodoError> {
+		payload.validate().map_err(map_validation_error)?;
+		self.repository
+			.update(id, payload)
+			.map_err(map_repository_error)
+	}
+
+	pub fn delete(&self, id: i32) -> Result<(), TodoError> {
+		self.repository.delete(id).map_err(map_repository_error)
+	}
+}